@@ -0,0 +1,426 @@
+use crate::utils::check_hres;
+use failure::Error;
+use std::ptr;
+use std::ptr::null_mut;
+use widestring::WideCString;
+use winapi::{
+    shared::{
+        ntdef::NULL,
+        rpcdce::{RPC_C_AUTHN_LEVEL_DEFAULT, SEC_WINNT_AUTH_IDENTITY_UNICODE},
+    },
+    um::{
+        combaseapi::{
+            CoCreateInstance, CoInitializeEx, CoInitializeSecurity, CoSetProxyBlanket,
+            CoUninitialize, CLSCTX_INPROC_SERVER,
+        },
+        objbase::{
+            COINIT_MULTITHREADED, RPC_C_AUTHN_WINNT, RPC_C_AUTHZ_NONE, RPC_C_IMP_LEVEL_ANONYMOUS,
+            RPC_C_IMP_LEVEL_DELEGATE, RPC_C_IMP_LEVEL_IDENTIFY, RPC_C_IMP_LEVEL_IMPERSONATE,
+        },
+        objidl::EOAC_NONE,
+        objidlbase::COAUTHIDENTITY,
+        wbemcli::{CLSID_WbemLocator, IID_IWbemLocator, IWbemLocator, IWbemServices},
+    },
+};
+
+/// The `RPC_C_IMP_LEVEL_*` a caller is granted on a remote connection, controlling how far the
+/// server may act on the caller's behalf.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImpersonationLevel {
+    Anonymous,
+    Identify,
+    Impersonate,
+    Delegate,
+}
+
+impl ImpersonationLevel {
+    fn as_raw(self) -> u32 {
+        match self {
+            ImpersonationLevel::Anonymous => RPC_C_IMP_LEVEL_ANONYMOUS,
+            ImpersonationLevel::Identify => RPC_C_IMP_LEVEL_IDENTIFY,
+            ImpersonationLevel::Impersonate => RPC_C_IMP_LEVEL_IMPERSONATE,
+            ImpersonationLevel::Delegate => RPC_C_IMP_LEVEL_DELEGATE,
+        }
+    }
+}
+
+/// The `RPC_C_AUTHN_LEVEL_*` used to secure calls to the remote `IWbemServices`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticationLevel {
+    Default,
+    None,
+    Connect,
+    Call,
+    Pkt,
+    PktIntegrity,
+    PktPrivacy,
+}
+
+impl AuthenticationLevel {
+    fn as_raw(self) -> u32 {
+        use winapi::shared::rpcdce::*;
+
+        match self {
+            AuthenticationLevel::Default => RPC_C_AUTHN_LEVEL_DEFAULT,
+            AuthenticationLevel::None => RPC_C_AUTHN_LEVEL_NONE,
+            AuthenticationLevel::Connect => RPC_C_AUTHN_LEVEL_CONNECT,
+            AuthenticationLevel::Call => RPC_C_AUTHN_LEVEL_CALL,
+            AuthenticationLevel::Pkt => RPC_C_AUTHN_LEVEL_PKT,
+            AuthenticationLevel::PktIntegrity => RPC_C_AUTHN_LEVEL_PKT_INTEGRITY,
+            AuthenticationLevel::PktPrivacy => RPC_C_AUTHN_LEVEL_PKT_PRIVACY,
+        }
+    }
+}
+
+/// An initialized COM library for the current thread, required before any `IWbemServices` call.
+///
+#[derive(Debug)]
+pub struct COMLibrary {
+    _use_constructor: (),
+}
+
+impl COMLibrary {
+    /// Initialize COM (multi-threaded apartment) and enable default process-wide security,
+    /// as required before `IWbemLocator::ConnectServer` can be called from this thread.
+    ///
+    pub fn new() -> Result<Self, Error> {
+        unsafe {
+            check_hres(CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED))?;
+
+            check_hres(CoInitializeSecurity(
+                ptr::null_mut(),
+                -1,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                RPC_C_AUTHN_LEVEL_DEFAULT,
+                RPC_C_IMP_LEVEL_IMPERSONATE,
+                ptr::null_mut(),
+                EOAC_NONE,
+                ptr::null_mut(),
+            ))?;
+        }
+
+        Ok(Self { _use_constructor: () })
+    }
+}
+
+impl Drop for COMLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            CoUninitialize();
+        }
+    }
+}
+
+/// A connection to a WMI namespace (local or remote), wrapping the underlying `IWbemServices`.
+///
+#[derive(Debug)]
+pub struct WMIConnection {
+    _com_con: COMLibrary,
+    svc: *mut IWbemServices,
+}
+
+impl WMIConnection {
+    /// Connect to the local `ROOT\CIMV2` namespace.
+    ///
+    pub fn new(com_lib: COMLibrary) -> Result<Self, Error> {
+        ConnectionBuilder::new().connect(com_lib)
+    }
+
+    /// Connect to the given namespace on the local machine, e.g. `ROOT\WMI`.
+    ///
+    pub fn with_namespace(namespace_path: impl Into<String>, com_lib: COMLibrary) -> Result<Self, Error> {
+        ConnectionBuilder::new()
+            .namespace_path(namespace_path)
+            .connect(com_lib)
+    }
+
+    /// Connect to a remote (or differently-credentialed) namespace. Equivalent to
+    /// `ConnectionBuilder::new().namespace_path(path).credentials(user, pass).connect(com_lib)`.
+    ///
+    pub fn with_remote(
+        com_lib: COMLibrary,
+        namespace_path: impl Into<String>,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<Self, Error> {
+        ConnectionBuilder::new()
+            .namespace_path(namespace_path)
+            .credentials(username, password)
+            .connect(com_lib)
+    }
+
+    fn with_namespace_and_auth(com_lib: COMLibrary, builder: ConnectionBuilder) -> Result<Self, Error> {
+        let svc = Self::connect_server(&builder)?;
+
+        let auth_identity = builder.build_auth_identity()?;
+        let p_identity = auth_identity
+            .as_ref()
+            .map_or(null_mut(), |identity| &identity.raw as *const _ as *mut _);
+
+        // Applied unconditionally: even with no explicit credentials, the impersonation and
+        // authentication levels still need to be set on the proxy for the connection to behave
+        // as configured (they default to `Impersonate`/`Default`, matching local-only use).
+        unsafe {
+            check_hres(CoSetProxyBlanket(
+                svc as *mut _,
+                RPC_C_AUTHN_WINNT,
+                RPC_C_AUTHZ_NONE,
+                null_mut(),
+                builder.authentication_level.as_raw() as i32,
+                builder.impersonation_level.as_raw() as i32,
+                p_identity,
+                0,
+            ))?;
+        }
+
+        Ok(Self::from_svc(com_lib, svc))
+    }
+
+    fn connect_server(builder: &ConnectionBuilder) -> Result<*mut IWbemServices, Error> {
+        let namespace_path = WideCString::from_str(&builder.namespace_path)?;
+        let username = builder.username.as_deref().map(WideCString::from_str).transpose()?;
+        let password = builder.password.as_deref().map(WideCString::from_str).transpose()?;
+        let authority = builder.authority.as_deref().map(WideCString::from_str).transpose()?;
+        let locale = builder.locale.as_deref().map(WideCString::from_str).transpose()?;
+
+        let mut p_loc = NULL as *mut IWbemLocator;
+
+        unsafe {
+            check_hres(CoCreateInstance(
+                &CLSID_WbemLocator,
+                ptr::null_mut(),
+                CLSCTX_INPROC_SERVER,
+                &IID_IWbemLocator,
+                &mut p_loc as *mut _ as _,
+            ))?;
+        }
+
+        let mut p_svc = NULL as *mut IWbemServices;
+
+        unsafe {
+            check_hres((*p_loc).ConnectServer(
+                namespace_path.as_ptr() as *mut _,
+                username.map_or(ptr::null_mut(), |s| s.as_ptr() as *mut _),
+                password.map_or(ptr::null_mut(), |s| s.as_ptr() as *mut _),
+                locale.map_or(ptr::null_mut(), |s| s.as_ptr() as *mut _),
+                0,
+                authority.map_or(ptr::null_mut(), |s| s.as_ptr() as *mut _),
+                ptr::null_mut(),
+                &mut p_svc,
+            ))?;
+
+            (*p_loc).Release();
+        }
+
+        Ok(p_svc)
+    }
+
+    fn from_svc(com_lib: COMLibrary, svc: *mut IWbemServices) -> Self {
+        Self {
+            _com_con: com_lib,
+            svc,
+        }
+    }
+
+    pub(crate) fn svc(&self) -> *mut IWbemServices {
+        self.svc
+    }
+}
+
+impl Drop for WMIConnection {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.svc).Release();
+        }
+    }
+}
+
+/// A builder for connecting to a (possibly remote, possibly credentialed) WMI namespace.
+///
+/// Unlike [`WMIConnection::new`], which only connects to the local `ROOT\CIMV2` namespace,
+/// this lets a caller target any namespace on any host, with its own credentials:
+///
+/// ```edition2018
+/// # fn example() -> Result<(), failure::Error> {
+/// # use wmi::*;
+/// let com_con = COMLibrary::new()?;
+///
+/// let wmi_con = ConnectionBuilder::new()
+///     .namespace_path(r"\\REMOTE-HOST\ROOT\CIMV2")
+///     .credentials("REMOTE-HOST\\Administrator", "hunter2")
+///     .impersonation_level(ImpersonationLevel::Impersonate)
+///     .authentication_level(AuthenticationLevel::PktPrivacy)
+///     .connect(com_con)?;
+/// # Ok(())
+/// # }
+///
+#[derive(Debug, Clone)]
+pub struct ConnectionBuilder {
+    namespace_path: String,
+    username: Option<String>,
+    password: Option<String>,
+    authority: Option<String>,
+    locale: Option<String>,
+    impersonation_level: ImpersonationLevel,
+    authentication_level: AuthenticationLevel,
+}
+
+impl Default for ConnectionBuilder {
+    fn default() -> Self {
+        Self {
+            namespace_path: r"ROOT\CIMV2".to_owned(),
+            username: None,
+            password: None,
+            authority: None,
+            locale: None,
+            impersonation_level: ImpersonationLevel::Impersonate,
+            authentication_level: AuthenticationLevel::Default,
+        }
+    }
+}
+
+impl ConnectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The UNC namespace to connect to, e.g. `\\HOST\ROOT\CIMV2` for a remote provider, or
+    /// `ROOT\CIMV2` (the default) for the local machine.
+    ///
+    pub fn namespace_path(mut self, namespace_path: impl Into<String>) -> Self {
+        self.namespace_path = namespace_path.into();
+        self
+    }
+
+    /// Credentials for the connection, as `DOMAIN\user` (or a bare `user`) and a password.
+    ///
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn authority(mut self, authority: impl Into<String>) -> Self {
+        self.authority = Some(authority.into());
+        self
+    }
+
+    pub fn locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    pub fn impersonation_level(mut self, level: ImpersonationLevel) -> Self {
+        self.impersonation_level = level;
+        self
+    }
+
+    pub fn authentication_level(mut self, level: AuthenticationLevel) -> Self {
+        self.authentication_level = level;
+        self
+    }
+
+    /// Connect to the configured namespace, via `IWbemLocator::ConnectServer` followed by
+    /// `CoSetProxyBlanket` to apply the configured authentication.
+    ///
+    pub fn connect(self, com_lib: COMLibrary) -> Result<WMIConnection, Error> {
+        WMIConnection::with_namespace_and_auth(com_lib, self)
+    }
+
+    /// Build the `COAUTHIDENTITY` for [`CoSetProxyBlanket`], if credentials were configured.
+    ///
+    /// `username` is split on a single `\` into `Domain` and `User`, matching the
+    /// `DOMAIN\user` form `CoSetProxyBlanket` expects; with no `\`, the whole string is the
+    /// user and `Domain` is empty. The returned [`AuthIdentity`] owns the UTF-16 buffers the
+    /// `COAUTHIDENTITY` points into, and must outlive the `CoSetProxyBlanket` call.
+    ///
+    fn build_auth_identity(&self) -> Result<Option<AuthIdentity>, Error> {
+        let username = match &self.username {
+            Some(username) => username,
+            None => return Ok(None),
+        };
+        let password = self.password.as_deref().unwrap_or("");
+
+        let (domain, user) = match username.find('\\') {
+            Some(idx) => (&username[..idx], &username[idx + 1..]),
+            None => ("", username.as_str()),
+        };
+
+        let user = WideCString::from_str(user)?;
+        let domain = WideCString::from_str(domain)?;
+        let password = WideCString::from_str(password)?;
+
+        let raw = COAUTHIDENTITY {
+            User: user.as_ptr() as *mut _,
+            UserLength: user.len() as u32,
+            Domain: domain.as_ptr() as *mut _,
+            DomainLength: domain.len() as u32,
+            Password: password.as_ptr() as *mut _,
+            PasswordLength: password.len() as u32,
+            Flags: SEC_WINNT_AUTH_IDENTITY_UNICODE,
+        };
+
+        Ok(Some(AuthIdentity {
+            _user: user,
+            _domain: domain,
+            _password: password,
+            raw,
+        }))
+    }
+}
+
+/// Heap-owned UTF-16 buffers backing a `COAUTHIDENTITY`, kept alive for as long as the
+/// identity itself (see [`ConnectionBuilder::build_auth_identity`]).
+///
+struct AuthIdentity {
+    _user: WideCString,
+    _domain: WideCString,
+    _password: WideCString,
+    raw: COAUTHIDENTITY,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_builder_targets_local_cimv2_with_impersonate() {
+        let builder = ConnectionBuilder::new();
+
+        assert_eq!(builder.namespace_path, r"ROOT\CIMV2");
+        assert_eq!(builder.impersonation_level, ImpersonationLevel::Impersonate);
+        assert_eq!(builder.authentication_level, AuthenticationLevel::Default);
+    }
+
+    #[test]
+    fn build_auth_identity_is_none_without_credentials() {
+        let builder = ConnectionBuilder::new();
+
+        assert!(builder.build_auth_identity().unwrap().is_none());
+    }
+
+    #[test]
+    fn build_auth_identity_splits_domain_from_username() {
+        let builder =
+            ConnectionBuilder::new().credentials("REMOTE-HOST\\Administrator", "hunter2");
+
+        let identity = builder.build_auth_identity().unwrap().unwrap();
+
+        assert_eq!(identity.raw.DomainLength as usize, "REMOTE-HOST".len());
+        assert_eq!(identity.raw.UserLength as usize, "Administrator".len());
+        assert_eq!(identity.raw.PasswordLength as usize, "hunter2".len());
+    }
+
+    #[test]
+    fn build_auth_identity_without_domain() {
+        let builder = ConnectionBuilder::new().credentials("Administrator", "hunter2");
+
+        let identity = builder.build_auth_identity().unwrap().unwrap();
+
+        assert_eq!(identity.raw.DomainLength, 0);
+        assert_eq!(identity.raw.UserLength as usize, "Administrator".len());
+    }
+}
@@ -0,0 +1,88 @@
+use serde::de::{self, Deserialize};
+use std::fmt;
+
+/// Intercepts the `deserialize_struct` call that `#[derive(Deserialize)]` emits, so that `T`'s
+/// WMI class name and field names (honoring `#[serde(rename)]`/`#[serde(rename_all)]`) can be
+/// read off without ever constructing a `T`.
+struct MetaDeserializer {
+    name: Option<&'static str>,
+    fields: Option<&'static [&'static str]>,
+}
+
+/// Not a real deserialization failure: returned as soon as [`MetaDeserializer`] has captured
+/// what it needs, to short-circuit the rest of the (never-constructed) value.
+#[derive(Debug)]
+struct MetaCaptured;
+
+impl fmt::Display for MetaCaptured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "struct metadata captured")
+    }
+}
+
+impl std::error::Error for MetaCaptured {}
+
+impl de::Error for MetaCaptured {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        MetaCaptured
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut MetaDeserializer {
+    type Error = MetaCaptured;
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.name = Some(name);
+        self.fields = Some(fields);
+
+        Err(MetaCaptured)
+    }
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(MetaCaptured)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Return `T`'s WMI class name and the list of its field names, as they'd appear in a
+/// `SELECT <fields> FROM <name>` query, without constructing an instance of `T`.
+///
+pub fn struct_name_and_fields<'de, T>() -> (String, Vec<String>)
+where
+    T: Deserialize<'de>,
+{
+    let mut meta = MetaDeserializer {
+        name: None,
+        fields: None,
+    };
+
+    // Always returns `Err(MetaCaptured)`: `MetaDeserializer` only implements enough of
+    // `Deserializer` to observe the single `deserialize_struct` call the derive macro makes.
+    let _ = T::deserialize(&mut meta);
+
+    let name = meta.name.unwrap_or_default().to_owned();
+    let fields = meta
+        .fields
+        .unwrap_or_default()
+        .iter()
+        .map(|f| (*f).to_owned())
+        .collect();
+
+    (name, fields)
+}
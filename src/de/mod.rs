@@ -0,0 +1,6 @@
+//! This module can only deserialize a property once [`IWbemClassWrapper::get_raw_property`] can
+//! hand it a declared [`CimType`](crate::query::CimType) to dispatch on, so it landed after that
+//! primitive existed rather than alongside the first caller that needed it.
+
+pub mod meta;
+pub mod wbem_class_de;
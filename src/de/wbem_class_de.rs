@@ -0,0 +1,342 @@
+use crate::query::{CimType, IWbemClassWrapper};
+use crate::safearray::safe_array_to_vec_of_strings;
+use crate::utils::check_hres;
+use failure::Error;
+use serde::de::{self, IntoDeserializer};
+use std::fmt;
+use std::ptr;
+use widestring::WideCString;
+use winapi::{
+    shared::wtypes::{VT_EMPTY, VT_NULL},
+    um::{oaidl::VARIANT, oleauto::VariantClear, wbemcli::IWbemClassObject},
+    Interface,
+};
+
+/// Deserialize a single WMI object into `T`.
+///
+/// `T` can be a concrete struct (matching a `SELECT field1, field2, ...`), or a generic
+/// `HashMap<String, Variant>` to inspect a result without declaring a struct. When a property is
+/// itself an embedded `IWbemClassObject` (as `TargetInstance` is on `__InstanceCreationEvent` and
+/// friends), it is deserialized the same way recursively, so a struct field typed as another
+/// `#[derive(Deserialize)]` struct works transparently.
+///
+pub fn from_wbem_class_obj<T>(wbem_class_obj: &IWbemClassWrapper) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+{
+    T::deserialize(ClassDeserializer {
+        obj: wbem_class_obj,
+    })
+    .map_err(Error::from)
+}
+
+/// Not a real deserialization failure: carries a human-readable reason through `serde`'s
+/// `de::Error` trait, then gets converted into a [`failure::Error`] at the [`from_wbem_class_obj`]
+/// boundary via `failure`'s blanket `From<E: std::error::Error>` impl.
+#[derive(Debug)]
+struct WmiDeError(String);
+
+impl fmt::Display for WmiDeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for WmiDeError {}
+
+impl de::Error for WmiDeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        WmiDeError(msg.to_string())
+    }
+}
+
+impl WmiDeError {
+    fn from_failure(e: Error) -> Self {
+        WmiDeError(e.to_string())
+    }
+}
+
+/// Deserializes a whole WMI object, by exposing its properties as a `serde` map/struct.
+struct ClassDeserializer<'a> {
+    obj: &'a IWbemClassWrapper,
+}
+
+impl<'a> ClassDeserializer<'a> {
+    fn property_map_access(self, names: Vec<String>) -> PropertyMapAccess<'a> {
+        PropertyMapAccess {
+            obj: self.obj,
+            names: names.into_iter(),
+            current: None,
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ClassDeserializer<'a> {
+    type Error = WmiDeError;
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let names = fields.iter().map(|f| (*f).to_owned()).collect();
+
+        visitor.visit_map(self.property_map_access(names))
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let names = self
+            .obj
+            .list_properties()
+            .map_err(WmiDeError::from_failure)?;
+
+        visitor.visit_map(self.property_map_access(names))
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+/// A `serde` `MapAccess` over a WMI object's property names, fetching each value lazily as it's
+/// requested.
+struct PropertyMapAccess<'a> {
+    obj: &'a IWbemClassWrapper,
+    names: std::vec::IntoIter<String>,
+    current: Option<String>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for PropertyMapAccess<'a> {
+    type Error = WmiDeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.names.next() {
+            Some(name) => {
+                let key = seed.deserialize(name.as_str().into_deserializer())?;
+                self.current = Some(name);
+
+                Ok(Some(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let name = self
+            .current
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+
+        let raw = RawProperty::fetch(self.obj, &name).map_err(WmiDeError::from_failure)?;
+
+        seed.deserialize(PropertyDeserializer { raw: &raw })
+    }
+}
+
+/// A property's raw `VARIANT` and declared [`CimType`], cleared on drop.
+struct RawProperty {
+    variant: VARIANT,
+    cim_type: CimType,
+}
+
+impl RawProperty {
+    fn fetch(obj: &IWbemClassWrapper, name: &str) -> Result<Self, Error> {
+        let (variant, cim_type) = obj.get_raw_property(name)?;
+
+        Ok(Self { variant, cim_type })
+    }
+
+    fn vt(&self) -> u32 {
+        unsafe { self.variant.n1.n2().vt as u32 }
+    }
+}
+
+impl Drop for RawProperty {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = VariantClear(&mut self.variant);
+        }
+    }
+}
+
+/// Deserializes a single property's value, choosing the `serde` `visit_*` call (and so the
+/// numeric width) from the property's declared [`CimType`] rather than from the `VARIANT`'s
+/// runtime tag. A `CIM_DATETIME` value is handed over as its string, exactly like a `CIM_STRING`;
+/// the declared type does not affect how it is parsed. An embedded object (e.g. `TargetInstance`)
+/// recurses into a nested [`ClassDeserializer`] so it can be deserialized straight into a struct
+/// or map.
+struct PropertyDeserializer<'a> {
+    raw: &'a RawProperty,
+}
+
+impl<'a> PropertyDeserializer<'a> {
+    /// Take ownership of the embedded `IWbemClassObject` behind a `CimType::Object` property.
+    fn embedded_object(&self) -> Result<IWbemClassWrapper, WmiDeError> {
+        unsafe {
+            let p_unk = *self.raw.variant.n1.n2().n3().punkVal();
+
+            if p_unk.is_null() {
+                return Err(WmiDeError::custom("embedded object property is null"));
+            }
+
+            let mut p_obj: *mut IWbemClassObject = ptr::null_mut();
+
+            check_hres((*p_unk).QueryInterface(
+                &IWbemClassObject::uuidof(),
+                &mut p_obj as *mut _ as *mut _,
+            ))
+            .map_err(WmiDeError::from_failure)?;
+
+            Ok(IWbemClassWrapper::new(ptr::Unique::new(p_obj)))
+        }
+    }
+
+    fn deserialize_embedded_as<'de, V>(
+        self,
+        on_wrapper: impl FnOnce(ClassDeserializer<'_>, V) -> Result<V::Value, WmiDeError>,
+        visitor: V,
+    ) -> Result<V::Value, WmiDeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.raw.cim_type {
+            CimType::Object => {
+                let embedded = self.embedded_object()?;
+
+                on_wrapper(ClassDeserializer { obj: &embedded }, visitor)
+            }
+            ref other => Err(WmiDeError::custom(format!(
+                "expected an embedded object, found CIM type {:?}",
+                other
+            ))),
+        }
+    }
+
+    /// Support for the one array shape actually exercised in this crate so far: a `SAFEARRAY` of
+    /// `BSTR`s (e.g. a multi-valued string property).
+    fn deserialize_array<'de, V>(&self, inner: &CimType, visitor: V) -> Result<V::Value, WmiDeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        match inner {
+            CimType::String | CimType::DateTime | CimType::Reference => {
+                let psa = unsafe { *self.raw.variant.n1.n2().n3().parray() };
+                let values =
+                    safe_array_to_vec_of_strings(psa).map_err(WmiDeError::from_failure)?;
+
+                visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter()))
+            }
+            other => Err(WmiDeError::custom(format!(
+                "deserializing arrays of CIM type {:?} is not supported yet",
+                other
+            ))),
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for PropertyDeserializer<'a> {
+    type Error = WmiDeError;
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.raw.vt() == VT_NULL as u32 || self.raw.vt() == VT_EMPTY as u32 {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_embedded_as(|d, v| de::Deserializer::deserialize_map(d, v), visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_embedded_as(|d, v| de::Deserializer::deserialize_map(d, v), visitor)
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        unsafe {
+            match &self.raw.cim_type {
+                CimType::Sint8 => visitor.visit_i8(*self.raw.variant.n1.n2().n3().cVal() as i8),
+                CimType::Uint8 => visitor.visit_u8(*self.raw.variant.n1.n2().n3().bVal()),
+                CimType::Sint16 => visitor.visit_i16(*self.raw.variant.n1.n2().n3().iVal()),
+                CimType::Uint16 => visitor.visit_u16(*self.raw.variant.n1.n2().n3().uiVal()),
+                CimType::Sint32 => visitor.visit_i32(*self.raw.variant.n1.n2().n3().lVal()),
+                CimType::Uint32 => visitor.visit_u32(*self.raw.variant.n1.n2().n3().ulVal()),
+                CimType::Sint64 => visitor.visit_i64(*self.raw.variant.n1.n2().n3().llVal()),
+                CimType::Uint64 => visitor.visit_u64(*self.raw.variant.n1.n2().n3().ullVal()),
+                CimType::Real32 => visitor.visit_f32(*self.raw.variant.n1.n2().n3().fltVal()),
+                CimType::Real64 => visitor.visit_f64(*self.raw.variant.n1.n2().n3().dblVal()),
+                CimType::Bool => visitor.visit_bool(*self.raw.variant.n1.n2().n3().boolVal() != 0),
+                // `CIM_DATETIME` travels over the wire as a BSTR (e.g.
+                // `20240102030405.000000+060`) and is visited as a plain string, the same as
+                // `CIM_STRING`: the target type (e.g. `WMIDateTime`) parses it from the string,
+                // and a `CIM_STRING` property can be deserialized into it the same way.
+                CimType::String | CimType::DateTime | CimType::Reference => {
+                    let bstr = *self.raw.variant.n1.n2().n3().bstrVal();
+
+                    if bstr.is_null() {
+                        visitor.visit_none()
+                    } else {
+                        let s = WideCString::from_ptr_str(bstr).to_string_lossy();
+
+                        visitor.visit_string(s)
+                    }
+                }
+                CimType::Object => {
+                    let embedded = self.embedded_object()?;
+
+                    de::Deserializer::deserialize_map(ClassDeserializer { obj: &embedded }, visitor)
+                }
+                CimType::Array(inner) => self.deserialize_array(inner, visitor),
+                CimType::Unknown(raw) => {
+                    Err(WmiDeError::custom(format!("unrecognized CIM type {}", raw)))
+                }
+            }
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,62 @@
+use crate::de::wbem_class_de::from_wbem_class_obj;
+use crate::query::QueryResultEnumerator;
+use failure::Error;
+use serde::de;
+use std::marker::PhantomData;
+
+#[cfg(feature = "async-query")]
+use crate::query_sink::ExecQueryAsyncResultStream;
+#[cfg(feature = "async-query")]
+use futures::stream::{Stream, StreamExt};
+
+/// An iterator which deserializes each raw event object yielded by a
+/// [`QueryResultEnumerator`](crate::query::QueryResultEnumerator) that was created from an
+/// `ExecNotificationQuery` call.
+///
+/// Unlike [`raw_query`](crate::WMIConnection::raw_query), this does not collect into a `Vec`:
+/// an event subscription has no natural end, so results are produced lazily as WMI delivers them.
+///
+pub struct NotificationIterator<'a, T> {
+    enumerator: QueryResultEnumerator<'a>,
+    _marker: PhantomData<T>,
+}
+
+impl<'a, T> NotificationIterator<'a, T> {
+    pub(crate) fn new(enumerator: QueryResultEnumerator<'a>) -> Self {
+        Self {
+            enumerator,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for NotificationIterator<'a, T>
+where
+    T: de::DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.enumerator.next().map(|item| match item {
+            Ok(wbem_class_obj) => from_wbem_class_obj(&wbem_class_obj).map_err(Error::from),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+/// A [`Stream`](futures::Stream) of deserialized events, backed by an
+/// [`ExecQueryAsyncResultStream`](crate::query_sink::ExecQueryAsyncResultStream) that was
+/// registered via `ExecNotificationQueryAsync`.
+///
+#[cfg(feature = "async-query")]
+pub fn typed_notification_stream<T>(
+    stream: ExecQueryAsyncResultStream<'_>,
+) -> impl Stream<Item = Result<T, Error>> + '_
+where
+    T: de::DeserializeOwned,
+{
+    stream.map(|item| match item {
+        Ok(wbem_class_obj) => from_wbem_class_obj(&wbem_class_obj).map_err(Error::from),
+        Err(e) => Err(e),
+    })
+}
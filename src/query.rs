@@ -1,31 +1,175 @@
 use crate::de::wbem_class_de::from_wbem_class_obj;
 use crate::{
     connection::WMIConnection,
-    consts::{WBEM_FLAG_ALWAYS, WBEM_FLAG_NONSYSTEM_ONLY},
+    consts::{WBEM_FLAG_ALWAYS, WBEM_FLAG_NONSYSTEM_ONLY, WBEM_FLAG_SYSTEM_ONLY},
     de::meta::struct_name_and_fields,
     safearray::{safe_array_to_vec_of_strings, SafeArrayDestroy},
     utils::check_hres,
+    variant::Variant,
 };
 use failure::Error;
 use log::debug;
 use serde::de;
 use std::collections::HashMap;
+use std::mem;
+use std::os::raw::c_long;
 use std::{ptr, ptr::Unique};
 use widestring::WideCString;
 use winapi::{
     shared::ntdef::NULL,
     um::{
-        oaidl::SAFEARRAY,
+        oaidl::{SAFEARRAY, VARIANT},
+        oleauto::VariantClear,
         wbemcli::{IEnumWbemClassObject, IWbemClassObject},
         wbemcli::{WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY, WBEM_INFINITE},
+        wbemcli::{
+            CIMTYPE, CIM_BOOLEAN, CIM_CHAR16, CIM_DATETIME, CIM_FLAG_ARRAY, CIM_OBJECT,
+            CIM_REAL32, CIM_REAL64, CIM_REFERENCE, CIM_SINT16, CIM_SINT32, CIM_SINT64,
+            CIM_SINT8, CIM_STRING, CIM_UINT16, CIM_UINT32, CIM_UINT64, CIM_UINT8,
+        },
     },
 };
 
+#[cfg(feature = "async-query")]
+use crate::query_sink::{ExecQueryAsyncResultStream, QuerySink};
+#[cfg(feature = "async-query")]
+use futures::stream::{Stream, StreamExt};
+#[cfg(feature = "async-query")]
+use winapi::um::wbemcli::WBEM_FLAG_BIDIRECTIONAL;
+
+use crate::notification::NotificationIterator;
+#[cfg(feature = "async-query")]
+use crate::notification::typed_notification_stream;
+
+/// A single value (and, for comparison operators, the value it's compared against) that can
+/// appear on the right-hand side of a WQL `WHERE` condition.
+///
+#[derive(Clone)]
 pub enum FilterValue {
     Bool(bool),
     Number(i64),
     Str(&'static str),
     String(String),
+    /// `field LIKE "..."`, for wildcard matches (e.g. process names).
+    Like(String),
+    /// `field > value`, for numeric or datetime fields.
+    Greater(Box<FilterValue>),
+    /// `field < value`, for numeric or datetime fields.
+    Less(Box<FilterValue>),
+    /// `field >= value`, for numeric or datetime fields.
+    GreaterEqual(Box<FilterValue>),
+    /// `field <= value`, for numeric or datetime fields.
+    LessEqual(Box<FilterValue>),
+    /// `field ISA 'Class'`.
+    IsA(&'static str),
+    /// `field IS NULL`.
+    Null,
+    /// `field IS NOT NULL`.
+    NotNull,
+    /// `field IN (v1, v2, ...)`.
+    In(Vec<FilterValue>),
+}
+
+impl FilterValue {
+    /// Render just the literal (e.g. `"a"`, `42`, `true`), with no field name or operator.
+    fn to_sql_literal(&self) -> String {
+        match self {
+            FilterValue::Bool(b) => {
+                if *b {
+                    "true".to_owned()
+                } else {
+                    "false".to_owned()
+                }
+            }
+            FilterValue::Number(n) => format!("{}", n),
+            FilterValue::Str(s) => format!("\"{}\"", s),
+            FilterValue::String(s) => format!("\"{}\"", s),
+            FilterValue::Like(s) => format!("\"{}\"", s),
+            // `ISA` takes a class name in single quotes, same as `build_notification_query`'s
+            // `TargetInstance ISA '...'`.
+            FilterValue::IsA(s) => format!("'{}'", s),
+            FilterValue::Greater(v)
+            | FilterValue::Less(v)
+            | FilterValue::GreaterEqual(v)
+            | FilterValue::LessEqual(v) => v.to_sql_literal(),
+            FilterValue::Null | FilterValue::NotNull => String::new(),
+            FilterValue::In(values) => format!(
+                "({})",
+                values
+                    .iter()
+                    .map(FilterValue::to_sql_literal)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Render a full `field <op> value` condition for this filter value.
+    fn to_condition(&self, field: &str) -> String {
+        match self {
+            FilterValue::Like(_) => format!("{} LIKE {}", field, self.to_sql_literal()),
+            FilterValue::Greater(_) => format!("{} > {}", field, self.to_sql_literal()),
+            FilterValue::Less(_) => format!("{} < {}", field, self.to_sql_literal()),
+            FilterValue::GreaterEqual(_) => format!("{} >= {}", field, self.to_sql_literal()),
+            FilterValue::LessEqual(_) => format!("{} <= {}", field, self.to_sql_literal()),
+            FilterValue::IsA(_) => format!("{} ISA {}", field, self.to_sql_literal()),
+            FilterValue::Null => format!("{} IS NULL", field),
+            FilterValue::NotNull => format!("{} IS NOT NULL", field),
+            FilterValue::In(_) => format!("{} IN {}", field, self.to_sql_literal()),
+            FilterValue::Bool(_) | FilterValue::Number(_) | FilterValue::Str(_) | FilterValue::String(_) => {
+                format!("{} = {}", field, self.to_sql_literal())
+            }
+        }
+    }
+}
+
+/// A boolean expression tree over [`FilterValue`] conditions, used to build a WQL `WHERE`
+/// clause with arbitrary combinations of `AND`/`OR`/`NOT`.
+///
+pub enum FilterExpr {
+    Condition(String, FilterValue),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Build an `AND` of one condition per entry of a flat map (an equality for `Bool`, `Number`,
+    /// `Str` and `String` values), as used by the legacy
+    /// [`filtered_query`](crate::WMIConnection::filtered_query) convenience method.
+    fn and_of(filters: &HashMap<String, FilterValue>) -> Self {
+        let mut fields: Vec<&String> = filters.keys().collect();
+
+        // Just to make testing easier.
+        fields.sort();
+
+        FilterExpr::And(
+            fields
+                .into_iter()
+                .map(|field| FilterExpr::Condition(field.clone(), filters[field].clone()))
+                .collect(),
+        )
+    }
+
+    fn to_sql(&self) -> String {
+        match self {
+            FilterExpr::Condition(field, value) => value.to_condition(field),
+            FilterExpr::And(exprs) => exprs
+                .iter()
+                .map(FilterExpr::to_sql)
+                .collect::<Vec<_>>()
+                .join(" AND "),
+            FilterExpr::Or(exprs) => format!(
+                "({})",
+                exprs
+                    .iter()
+                    .map(FilterExpr::to_sql)
+                    .collect::<Vec<_>>()
+                    .join(" OR ")
+            ),
+            FilterExpr::Not(expr) => format!("NOT ({})", expr.to_sql()),
+        }
+    }
 }
 
 /// Build an SQL query for the given filters, over the given type (using it's name and fields).
@@ -52,43 +196,16 @@ pub enum FilterValue {
 /// "SELECT Caption, Debug FROM Win32_OperatingSystem";
 /// ```
 ///
-fn build_query<'de, T>(filters: Option<&HashMap<String, FilterValue>>) -> String
+fn build_query<'de, T>(filter: Option<&FilterExpr>) -> String
 where
     T: de::Deserialize<'de>,
 {
     let (name, fields) = struct_name_and_fields::<T>();
 
-    let optional_where_clause = match filters {
+    let optional_where_clause = match filter {
         None => String::new(),
-        Some(filters) => {
-            if filters.is_empty() {
-                String::new()
-            } else {
-                let mut conditions = vec![];
-
-                for (field, filter) in filters {
-                    let value = match filter {
-                        FilterValue::Bool(b) => {
-                            if *b {
-                                "true".to_owned()
-                            } else {
-                                "false".to_owned()
-                            }
-                        }
-                        FilterValue::Number(n) => format!("{}", n),
-                        FilterValue::Str(s) => format!("\"{}\"", s),
-                        FilterValue::String(s) => format!("\"{}\"", s),
-                    };
-
-                    conditions.push(format!("{} = {}", field, value));
-                }
-
-                // Just to make testing easier.
-                conditions.sort();
-
-                format!("WHERE {}", conditions.join(" AND "))
-            }
-        }
+        Some(FilterExpr::And(exprs)) if exprs.is_empty() => String::new(),
+        Some(expr) => format!("WHERE {}", expr.to_sql()),
     };
 
     let query_text = format!(
@@ -101,6 +218,21 @@ where
     query_text
 }
 
+/// Build a `WITHIN 1 WHERE TargetInstance ISA '...'` creation-event query for the given type,
+/// as used by the [`notification_query`](WMIConnection::notification_query) convenience method.
+///
+fn build_notification_query<'de, T>() -> String
+where
+    T: de::Deserialize<'de>,
+{
+    let (class_name, _) = struct_name_and_fields::<T>();
+
+    format!(
+        "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA '{}'",
+        class_name
+    )
+}
+
 pub struct QueryResultEnumerator<'a> {
     wmi_con: &'a WMIConnection,
     p_enumerator: Option<Unique<IEnumWbemClassObject>>,
@@ -194,7 +326,233 @@ impl WMIConnection {
     where
         T: de::DeserializeOwned,
     {
-        let query_text = build_query::<T>(Some(&filters));
+        let expr = FilterExpr::and_of(filters);
+        let query_text = build_query::<T>(Some(&expr));
+
+        self.raw_query(&query_text)
+    }
+
+    /// Execute the given query asynchronously and return a stream of WMI pointers.
+    /// Like [`exec_query_native_wrapper`](Self::exec_query_native_wrapper), it's better to use
+    /// the other `*_async` query methods, since this is relatively low level.
+    ///
+    #[cfg(feature = "async-query")]
+    pub fn exec_query_async_native_wrapper(
+        &self,
+        query: impl AsRef<str>,
+    ) -> Result<ExecQueryAsyncResultStream<'_>, Error> {
+        let query_language = WideCString::from_str("WQL")?;
+        let query = WideCString::from_str(query)?;
+
+        let (sink, receiver) = QuerySink::new();
+        let p_sink = sink.as_raw();
+
+        unsafe {
+            check_hres((*self.svc()).ExecQueryAsync(
+                query_language.as_ptr() as *mut _,
+                query.as_ptr() as *mut _,
+                WBEM_FLAG_BIDIRECTIONAL as i32,
+                ptr::null_mut(),
+                p_sink,
+            ))?;
+        }
+
+        debug!("Registered async sink {:?}", p_sink);
+
+        Ok(ExecQueryAsyncResultStream::new(self, sink, receiver))
+    }
+
+    /// Execute a free-text query asynchronously and deserialize the results as they arrive.
+    ///
+    /// ```edition2018
+    /// # async fn example() -> Result<(), failure::Error> {
+    /// # use wmi::*;
+    /// # use futures::stream::StreamExt;
+    /// # use std::collections::HashMap;
+    /// # let con = WMIConnection::new(COMLibrary::new()?.into())?;
+    /// let mut stream = con.async_raw_query::<HashMap<String, Variant>>("SELECT Name FROM Win32_OperatingSystem");
+    ///
+    /// while let Some(item) = stream.next().await {
+    ///     let _item: HashMap<String, Variant> = item?;
+    /// }
+    /// # Ok(())
+    /// # }
+    ///
+    #[cfg(feature = "async-query")]
+    pub fn async_raw_query<T>(
+        &self,
+        query: impl AsRef<str>,
+    ) -> impl Stream<Item = Result<T, Error>> + '_
+    where
+        T: de::DeserializeOwned,
+    {
+        let stream = match self.exec_query_async_native_wrapper(query) {
+            Ok(stream) => stream,
+            Err(e) => return futures::stream::once(async { Err(e) }).left_stream(),
+        };
+
+        stream
+            .map(|item| match item {
+                Ok(wbem_class_obj) => from_wbem_class_obj(&wbem_class_obj).map_err(Error::from),
+                Err(e) => Err(e),
+            })
+            .right_stream()
+    }
+
+    /// Query all the objects of type T asynchronously.
+    ///
+    #[cfg(feature = "async-query")]
+    pub fn async_query<T>(&self) -> impl Stream<Item = Result<T, Error>> + '_
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = build_query::<T>(None);
+
+        self.async_raw_query(query_text)
+    }
+
+    /// Query all the objects of type T asynchronously, while filtering according to `filters`.
+    ///
+    #[cfg(feature = "async-query")]
+    pub fn async_filtered_query<T>(
+        &self,
+        filters: &HashMap<String, FilterValue>,
+    ) -> impl Stream<Item = Result<T, Error>> + '_
+    where
+        T: de::DeserializeOwned,
+    {
+        let expr = FilterExpr::and_of(filters);
+        let query_text = build_query::<T>(Some(&expr));
+
+        self.async_raw_query(query_text)
+    }
+
+    /// Register an event subscription and return an iterator of the raw WMI pointers for each
+    /// event as it arrives. It's better to use [`notification_query`](Self::notification_query)
+    /// or [`raw_notification_query`](Self::raw_notification_query), since this is relatively low level.
+    ///
+    pub fn exec_notification_query_native_wrapper(
+        &self,
+        query: impl AsRef<str>,
+    ) -> Result<QueryResultEnumerator, Error> {
+        let query_language = WideCString::from_str("WQL")?;
+        let query = WideCString::from_str(query)?;
+
+        let mut p_enumerator = NULL as *mut IEnumWbemClassObject;
+
+        unsafe {
+            check_hres((*self.svc()).ExecNotificationQuery(
+                query_language.as_ptr() as *mut _,
+                query.as_ptr() as *mut _,
+                (WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY) as i32,
+                ptr::null_mut(),
+                &mut p_enumerator,
+            ))?;
+        }
+
+        debug!("Registered notification enumerator {:?}", p_enumerator);
+
+        Ok(QueryResultEnumerator {
+            wmi_con: self,
+            p_enumerator: Unique::new(p_enumerator),
+        })
+    }
+
+    /// Subscribe to an event query (e.g. `SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE
+    /// TargetInstance ISA 'Win32_Process'`) and deserialize each event as it arrives.
+    ///
+    /// The event's `TargetInstance` property is itself an embedded `IWbemClassObject`; the
+    /// deserializer ([`de::wbem_class_de`](crate::de::wbem_class_de)) recurses into it, so a
+    /// `TargetInstance` field can be typed as the target instance's own struct (e.g.
+    /// `TargetInstance: Win32_Process`) instead of a raw `HashMap<String, Variant>`.
+    ///
+    pub fn raw_notification_query<T>(
+        &self,
+        query: impl AsRef<str>,
+    ) -> Result<NotificationIterator<T>, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let enumerator = self.exec_notification_query_native_wrapper(query)?;
+
+        Ok(NotificationIterator::new(enumerator))
+    }
+
+    /// Subscribe to creation events for the instances of type `T`, building the `WITHIN 1
+    /// WHERE TargetInstance ISA '...'` query from `T`'s class name, the way [`query`](Self::query)
+    /// builds a `SELECT` from `T`'s fields.
+    ///
+    pub fn notification_query<T>(&self) -> Result<NotificationIterator<T>, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = build_notification_query::<T>();
+
+        self.raw_notification_query(&query_text)
+    }
+
+    /// Subscribe to an event query asynchronously, yielding a `Stream` of deserialized events.
+    ///
+    #[cfg(feature = "async-query")]
+    pub fn async_raw_notification_query<T>(
+        &self,
+        query: impl AsRef<str>,
+    ) -> Result<impl Stream<Item = Result<T, Error>> + '_, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_language = WideCString::from_str("WQL")?;
+        let query = WideCString::from_str(query)?;
+
+        let (sink, receiver) = QuerySink::new();
+        let p_sink = sink.as_raw();
+
+        unsafe {
+            check_hres((*self.svc()).ExecNotificationQueryAsync(
+                query_language.as_ptr() as *mut _,
+                query.as_ptr() as *mut _,
+                WBEM_FLAG_BIDIRECTIONAL as i32,
+                ptr::null_mut(),
+                p_sink,
+            ))?;
+        }
+
+        let stream = ExecQueryAsyncResultStream::new(self, sink, receiver);
+
+        Ok(typed_notification_stream(stream))
+    }
+
+    /// Return all the objects of type `T` associated with the object at `path` (as obtained from
+    /// [`IWbemClassWrapper::path`]), via an `ASSOCIATORS OF` query.
+    ///
+    /// Deliberate deviation from a literal `associators<T, A>(&self, path: &str)` signature: a
+    /// separate `A` would let a caller pick a `ResultClass` independent of the deserialization
+    /// target `T`, which could silently mismatch the `WHERE` clause against what's actually being
+    /// deserialized. `ResultClass` is derived from `T` directly instead, the same way `query`
+    /// derives its `SELECT`/`FROM` from `T`.
+    ///
+    pub fn associators<T>(&self, path: &str) -> Result<Vec<T>, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let (result_class, _) = struct_name_and_fields::<T>();
+
+        let query_text = format!(
+            "ASSOCIATORS OF {{{}}} WHERE ResultClass = {}",
+            path, result_class
+        );
+
+        self.raw_query(&query_text)
+    }
+
+    /// Return all the association objects (e.g. `Win32_DiskDriveToDiskPartition`) that reference
+    /// the object at `path`, via a `REFERENCES OF` query.
+    ///
+    pub fn references<T>(&self, path: &str) -> Result<Vec<T>, Error>
+    where
+        T: de::DeserializeOwned,
+    {
+        let query_text = format!("REFERENCES OF {{{}}}", path);
 
         self.raw_query(&query_text)
     }
@@ -254,6 +612,198 @@ impl IWbemClassWrapper {
 
         res
     }
+
+    /// Return the names of the system properties (e.g. `__PATH`, `__RELPATH`, `__CLASS`) of
+    /// the given object.
+    ///
+    pub fn list_system_properties(&self) -> Result<Vec<String>, Error> {
+        let mut p_names = NULL as *mut SAFEARRAY;
+
+        let ptr = self.inner.unwrap().as_ptr();
+
+        unsafe {
+            check_hres((*ptr).GetNames(
+                ptr::null(),
+                WBEM_FLAG_ALWAYS | WBEM_FLAG_SYSTEM_ONLY,
+                ptr::null_mut(),
+                &mut p_names,
+            ))
+        }?;
+
+        let res = safe_array_to_vec_of_strings(p_names);
+
+        unsafe {
+            check_hres(SafeArrayDestroy(p_names))?;
+        }
+
+        res
+    }
+
+    /// Return the value of a `BSTR`-typed system property, such as `__PATH` or `__RELPATH`.
+    ///
+    fn get_system_bstr_property(&self, name: &str) -> Result<String, Error> {
+        let ptr = self.inner.unwrap().as_ptr();
+        let wide_name = WideCString::from_str(name)?;
+
+        let mut vt_prop: VARIANT = unsafe { mem::zeroed() };
+
+        unsafe {
+            check_hres((*ptr).Get(
+                wide_name.as_ptr() as *mut _,
+                0,
+                &mut vt_prop,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            ))?;
+        }
+
+        let value = unsafe {
+            let bstr_val = *vt_prop.n1.n2().n3().bstrVal();
+
+            WideCString::from_ptr_str(bstr_val).to_string_lossy()
+        };
+
+        unsafe {
+            check_hres(VariantClear(&mut vt_prop))?;
+        }
+
+        Ok(value)
+    }
+
+    /// The full `__PATH` of this object (e.g. `\\.\root\cimv2:Win32_DiskDrive.DeviceID="..."`),
+    /// usable as the anchor of an `associators`/`references` query.
+    ///
+    pub fn path(&self) -> Result<String, Error> {
+        self.get_system_bstr_property("__PATH")
+    }
+
+    /// The `__RELPATH` of this object, relative to the current namespace.
+    ///
+    pub fn relative_path(&self) -> Result<String, Error> {
+        self.get_system_bstr_property("__RELPATH")
+    }
+
+    /// Return the value, declared CIM type, and qualifier flavor of a single property.
+    ///
+    /// The declared [`CimType`] is exposed rather than guessed from the `VARIANT`'s runtime
+    /// type, so a caller can tell e.g. a `CIM_DATETIME` string apart from a plain `CIM_STRING`
+    /// without sniffing its contents. [`de::wbem_class_de`](crate::de::wbem_class_de) uses the
+    /// same declared type internally (via [`get_raw_property`](Self::get_raw_property)) to pick
+    /// the right numeric width, but passes `CIM_DATETIME` values on as plain strings; this
+    /// method is for callers that want the schema directly, outside of a full query round-trip.
+    ///
+    pub fn get_property(&self, name: &str) -> Result<(Variant, CimType, i32), Error> {
+        let ptr = self.inner.unwrap().as_ptr();
+        let wide_name = WideCString::from_str(name)?;
+
+        let mut vt_prop: VARIANT = unsafe { mem::zeroed() };
+        let mut cim_type: CIMTYPE = 0;
+        let mut flavor: c_long = 0;
+
+        unsafe {
+            check_hres((*ptr).Get(
+                wide_name.as_ptr() as *mut _,
+                0,
+                &mut vt_prop,
+                &mut cim_type,
+                &mut flavor,
+            ))?;
+        }
+
+        let variant = Variant::from_variant(&vt_prop);
+
+        unsafe {
+            check_hres(VariantClear(&mut vt_prop))?;
+        }
+
+        Ok((variant?, CimType::from_raw(cim_type), flavor))
+    }
+
+    /// Return just the declared CIM type of a property, without decoding its value.
+    ///
+    pub fn property_type(&self, name: &str) -> Result<CimType, Error> {
+        self.get_property(name).map(|(_, cim_type, _)| cim_type)
+    }
+
+    /// Return the raw `VARIANT` and declared [`CimType`] of a property, without converting it
+    /// to a [`Variant`] or clearing it.
+    ///
+    /// Used by [`de::wbem_class_de`](crate::de::wbem_class_de) to pick the right numeric width
+    /// and to recurse into embedded objects (e.g. `TargetInstance`); callers that just want the
+    /// decoded value should use [`get_property`](Self::get_property) instead. The caller owns
+    /// the returned `VARIANT` and is responsible for clearing it.
+    ///
+    pub(crate) fn get_raw_property(&self, name: &str) -> Result<(VARIANT, CimType), Error> {
+        let ptr = self.inner.unwrap().as_ptr();
+        let wide_name = WideCString::from_str(name)?;
+
+        let mut vt_prop: VARIANT = unsafe { mem::zeroed() };
+        let mut cim_type: CIMTYPE = 0;
+
+        unsafe {
+            check_hres((*ptr).Get(
+                wide_name.as_ptr() as *mut _,
+                0,
+                &mut vt_prop,
+                &mut cim_type,
+                ptr::null_mut(),
+            ))?;
+        }
+
+        Ok((vt_prop, CimType::from_raw(cim_type)))
+    }
+}
+
+/// The declared CIM type of a WMI property, as reported by `IWbemClassObject::Get`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CimType {
+    Sint8,
+    Uint8,
+    Sint16,
+    Uint16,
+    Sint32,
+    Uint32,
+    Sint64,
+    Uint64,
+    Real32,
+    Real64,
+    Bool,
+    String,
+    DateTime,
+    Reference,
+    Object,
+    /// The `CIM_FLAG_ARRAY` bit was set; the inner type is the element type.
+    Array(Box<CimType>),
+    /// A CIM type this crate doesn't yet have a name for.
+    Unknown(CIMTYPE),
+}
+
+impl CimType {
+    fn from_raw(raw: CIMTYPE) -> Self {
+        if raw & CIM_FLAG_ARRAY != 0 {
+            return CimType::Array(Box::new(Self::from_raw(raw & !CIM_FLAG_ARRAY)));
+        }
+
+        match raw {
+            CIM_SINT8 => CimType::Sint8,
+            CIM_UINT8 => CimType::Uint8,
+            CIM_SINT16 => CimType::Sint16,
+            CIM_UINT16 => CimType::Uint16,
+            CIM_SINT32 => CimType::Sint32,
+            CIM_UINT32 => CimType::Uint32,
+            CIM_SINT64 => CimType::Sint64,
+            CIM_UINT64 => CimType::Uint64,
+            CIM_REAL32 => CimType::Real32,
+            CIM_REAL64 => CimType::Real64,
+            CIM_BOOLEAN => CimType::Bool,
+            CIM_STRING | CIM_CHAR16 => CimType::String,
+            CIM_DATETIME => CimType::DateTime,
+            CIM_REFERENCE => CimType::Reference,
+            CIM_OBJECT => CimType::Object,
+            other => CimType::Unknown(other),
+        }
+    }
 }
 
 impl Drop for IWbemClassWrapper {
@@ -403,6 +953,24 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "async-query")]
+    #[test]
+    fn it_can_query_a_struct_async() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_OperatingSystem {
+            Caption: String,
+        }
+
+        let results: Vec<Result<Win32_OperatingSystem, Error>> =
+            futures::executor::block_on(wmi_con.async_query::<Win32_OperatingSystem>().collect());
+
+        for os in results {
+            assert_eq!(os.unwrap().Caption, "Microsoft Windows 10 Pro");
+        }
+    }
+
     #[test]
     fn it_fails_gracefully_when_querying_a_struct() {
         let wmi_con = wmi_con();
@@ -417,6 +985,36 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn it_builds_correct_notification_query() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let query = build_notification_query::<Win32_Process>();
+
+        assert_eq!(
+            query,
+            r#"SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'"#
+        );
+    }
+
+    #[test]
+    fn it_can_register_a_notification_query() {
+        let wmi_con = wmi_con();
+
+        // Just check that the subscription is accepted and released cleanly on drop;
+        // waiting for a real process-creation event here would make the test flaky.
+        let enumerator = wmi_con
+            .exec_notification_query_native_wrapper(
+                "SELECT * FROM __InstanceCreationEvent WITHIN 1 WHERE TargetInstance ISA 'Win32_Process'",
+            )
+            .unwrap();
+
+        drop(enumerator);
+    }
+
     #[test]
     fn it_builds_correct_query_without_filters() {
         #[derive(Deserialize, Debug)]
@@ -444,13 +1042,189 @@ mod tests {
         filters.insert("C3".to_string(), FilterValue::Number(42));
         filters.insert("C4".to_string(), FilterValue::Bool(false));
 
-        let query = build_query::<Win32_OperatingSystem>(Some(&filters));
+        let expr = FilterExpr::and_of(&filters);
+        let query = build_query::<Win32_OperatingSystem>(Some(&expr));
         let select_part = r#"SELECT Caption FROM Win32_OperatingSystem "#.to_owned();
         let where_part = r#"WHERE C1 = "a" AND C2 = "b" AND C3 = 42 AND C4 = false"#;
 
         assert_eq!(query, select_part + where_part);
     }
 
+    #[test]
+    fn it_builds_a_like_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Condition(
+            "Name".to_owned(),
+            FilterValue::Like("cargo%".to_owned()),
+        );
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(query, r#"SELECT Name FROM Win32_Process WHERE Name LIKE "cargo%""#);
+    }
+
+    #[test]
+    fn it_builds_a_comparison_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_LogicalDisk {
+            FreeSpace: String,
+        }
+
+        let expr = FilterExpr::Condition(
+            "FreeSpace".to_owned(),
+            FilterValue::GreaterEqual(Box::new(FilterValue::Number(1024))),
+        );
+
+        let query = build_query::<Win32_LogicalDisk>(Some(&expr));
+
+        assert_eq!(
+            query,
+            r#"SELECT FreeSpace FROM Win32_LogicalDisk WHERE FreeSpace >= 1024"#
+        );
+    }
+
+    #[test]
+    fn it_builds_an_isa_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Condition(
+            "TargetInstance".to_owned(),
+            FilterValue::IsA("Win32_Process"),
+        );
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(
+            query,
+            r#"SELECT Name FROM Win32_Process WHERE TargetInstance ISA 'Win32_Process'"#
+        );
+    }
+
+    #[test]
+    fn it_builds_a_null_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Condition("Name".to_owned(), FilterValue::Null);
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(query, r#"SELECT Name FROM Win32_Process WHERE Name IS NULL"#);
+    }
+
+    #[test]
+    fn it_builds_a_not_null_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Condition("Name".to_owned(), FilterValue::NotNull);
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(
+            query,
+            r#"SELECT Name FROM Win32_Process WHERE Name IS NOT NULL"#
+        );
+    }
+
+    #[test]
+    fn it_builds_an_in_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Condition(
+            "Name".to_owned(),
+            FilterValue::In(vec![
+                FilterValue::Str("cargo.exe"),
+                FilterValue::Str("rustc.exe"),
+            ]),
+        );
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(
+            query,
+            r#"SELECT Name FROM Win32_Process WHERE Name IN ("cargo.exe", "rustc.exe")"#
+        );
+    }
+
+    #[test]
+    fn it_builds_an_or_condition() {
+        #[derive(Deserialize, Debug)]
+        struct Win32_Process {
+            Name: String,
+        }
+
+        let expr = FilterExpr::Or(vec![
+            FilterExpr::Condition("Name".to_owned(), FilterValue::Str("cargo.exe")),
+            FilterExpr::Condition("Name".to_owned(), FilterValue::Str("rustc.exe")),
+        ]);
+
+        let query = build_query::<Win32_Process>(Some(&expr));
+
+        assert_eq!(
+            query,
+            r#"SELECT Name FROM Win32_Process WHERE (Name = "cargo.exe" OR Name = "rustc.exe")"#
+        );
+    }
+
+    #[test]
+    fn it_can_get_property_cim_type() {
+        let wmi_con = wmi_con();
+
+        let enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT * FROM Win32_OperatingSystem")
+            .unwrap();
+
+        for res in enumerator {
+            let w = res.unwrap();
+
+            let (_, cim_type, _) = w.get_property("BootDevice").unwrap();
+            assert_eq!(cim_type, CimType::String);
+
+            let (_, cim_type, _) = w.get_property("Debug").unwrap();
+            assert_eq!(cim_type, CimType::Bool);
+        }
+    }
+
+    #[test]
+    fn it_can_query_associators() {
+        let wmi_con = wmi_con();
+
+        #[derive(Deserialize, Debug)]
+        struct Win32_DiskPartition {
+            Name: String,
+        }
+
+        let enumerator = wmi_con
+            .exec_query_native_wrapper("SELECT * FROM Win32_DiskDrive")
+            .unwrap();
+
+        for res in enumerator {
+            let w = res.unwrap();
+            let path = w.path().unwrap();
+
+            let partitions = wmi_con.associators::<Win32_DiskPartition>(&path).unwrap();
+
+            for partition in partitions {
+                assert!(!partition.Name.is_empty());
+            }
+        }
+    }
+
     #[test]
     fn it_can_filter() {
         let wmi_con = wmi_con();
@@ -0,0 +1,209 @@
+use crate::connection::WMIConnection;
+use crate::query::IWbemClassWrapper;
+use crate::utils::check_hres;
+use failure::Error;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use log::debug;
+use std::cell::UnsafeCell;
+use std::os::raw::c_long;
+use std::ptr::Unique;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use winapi::ctypes::c_void;
+use winapi::shared::ntdef::HRESULT;
+use winapi::shared::winerror::{E_NOINTERFACE, E_UNEXPECTED, S_OK};
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::wbemcli::{IWbemClassObject, IWbemObjectSink, IWbemObjectSinkVtbl};
+use winapi::Interface;
+
+/// An object sink which receives the asynchronous callbacks made by `IWbemServices::ExecQueryAsync`,
+/// and forwards each batch of `IWbemClassObject` pointers to an unbounded channel.
+///
+/// The receiving end of the channel is exposed as a [`Stream`](futures::Stream) of
+/// `Result<IWbemClassWrapper, failure::Error>` via [`ExecQueryAsyncResultStream`].
+///
+#[repr(C)]
+pub struct QuerySink {
+    inner: IWbemObjectSink,
+    ref_count: AtomicUsize,
+    sender: UnsafeCell<Option<UnboundedSender<Result<IWbemClassWrapper, Error>>>>,
+}
+
+unsafe impl Sync for QuerySink {}
+
+static SINK_VTBL: IWbemObjectSinkVtbl = IWbemObjectSinkVtbl {
+    parent: IUnknownVtbl {
+        QueryInterface: QuerySink::query_interface,
+        AddRef: QuerySink::add_ref,
+        Release: QuerySink::release,
+    },
+    Indicate: QuerySink::indicate,
+    SetStatus: QuerySink::set_status,
+};
+
+impl QuerySink {
+    /// Create a new sink (with a ref count of 1, as required by COM) and the receiving
+    /// end of its result stream.
+    ///
+    pub fn new() -> (Box<Self>, UnboundedReceiver<Result<IWbemClassWrapper, Error>>) {
+        let (tx, rx) = unbounded();
+
+        let sink = Box::new(Self {
+            inner: IWbemObjectSink {
+                lpVtbl: &SINK_VTBL,
+            },
+            ref_count: AtomicUsize::new(1),
+            sender: UnsafeCell::new(Some(tx)),
+        });
+
+        (sink, rx)
+    }
+
+    pub fn as_raw(&self) -> *mut IWbemObjectSink {
+        &self.inner as *const _ as *mut _
+    }
+
+    unsafe fn from_raw<'a>(this: *mut IWbemObjectSink) -> &'a Self {
+        &*(this as *const Self)
+    }
+
+    unsafe extern "system" fn query_interface(
+        this: *mut IUnknown,
+        riid: *const winapi::shared::guiddef::IID,
+        obj: *mut *mut c_void,
+    ) -> HRESULT {
+        if riid.is_null() || obj.is_null() {
+            return E_UNEXPECTED;
+        }
+
+        if *riid == IUnknown::uuidof() || *riid == IWbemObjectSink::uuidof() {
+            Self::add_ref(this);
+            *obj = this as *mut c_void;
+            S_OK
+        } else {
+            *obj = std::ptr::null_mut();
+            E_NOINTERFACE
+        }
+    }
+
+    unsafe extern "system" fn add_ref(this: *mut IUnknown) -> winapi::shared::ntdef::ULONG {
+        let me = Self::from_raw(this as *mut IWbemObjectSink);
+        (me.ref_count.fetch_add(1, Ordering::SeqCst) + 1) as _
+    }
+
+    unsafe extern "system" fn release(this: *mut IUnknown) -> winapi::shared::ntdef::ULONG {
+        let me = Self::from_raw(this as *mut IWbemObjectSink);
+        let prev = me.ref_count.fetch_sub(1, Ordering::SeqCst);
+
+        if prev == 1 {
+            drop(Box::from_raw(this as *mut Self));
+        }
+
+        (prev - 1) as _
+    }
+
+    unsafe extern "system" fn indicate(
+        this: *mut IWbemObjectSink,
+        object_count: c_long,
+        obj_array: *mut *mut IWbemClassObject,
+    ) -> HRESULT {
+        let me = Self::from_raw(this);
+
+        if let Some(sender) = &*me.sender.get() {
+            for i in 0..object_count {
+                let p = *obj_array.offset(i as isize);
+                (*p).AddRef();
+
+                let wrapper = IWbemClassWrapper::new(Unique::new(p));
+
+                // The receiver may have been dropped; there's nothing useful to do with the
+                // error, the subscription will be cancelled on our side via `Drop`.
+                let _ = sender.unbounded_send(Ok(wrapper));
+            }
+        }
+
+        S_OK
+    }
+
+    unsafe extern "system" fn set_status(
+        this: *mut IWbemObjectSink,
+        _flags: c_long,
+        hres: HRESULT,
+        _str_param: *mut winapi::um::oaidl::BSTR,
+        _obj_param: *mut IWbemClassObject,
+    ) -> HRESULT {
+        let me = Self::from_raw(this);
+
+        debug!("Async query finished with hres {:?}", hres);
+
+        if let Some(sender) = (*me.sender.get()).take() {
+            if let Err(e) = check_hres(hres) {
+                let _ = sender.unbounded_send(Err(e));
+            }
+            // Dropping `sender` closes the channel, ending the stream.
+        }
+
+        S_OK
+    }
+}
+
+/// A [`Stream`](futures::Stream) of raw [`IWbemClassWrapper`] results, backed by a [`QuerySink`].
+///
+/// Borrows the [`WMIConnection`] it was created from, which must outlive the stream: `Drop`
+/// issues a `CancelAsyncCall` through the connection's `IWbemServices` pointer.
+///
+pub struct ExecQueryAsyncResultStream<'a> {
+    wmi_con: &'a WMIConnection,
+    // `QuerySink` is COM-refcounted (see `QuerySink::release`) and WMI may `AddRef` it for as
+    // long as the async call is in flight, independently of this stream's lifetime. The `Box`
+    // is leaked into this raw pointer in `new` so that dropping the stream can't free memory
+    // COM still holds a reference to; `Drop` below releases our own reference instead.
+    sink: *mut QuerySink,
+    receiver: UnboundedReceiver<Result<IWbemClassWrapper, Error>>,
+}
+
+impl<'a> ExecQueryAsyncResultStream<'a> {
+    pub fn new(
+        wmi_con: &'a WMIConnection,
+        sink: Box<QuerySink>,
+        receiver: UnboundedReceiver<Result<IWbemClassWrapper, Error>>,
+    ) -> Self {
+        Self {
+            wmi_con,
+            sink: Box::into_raw(sink),
+            receiver,
+        }
+    }
+}
+
+impl<'a> futures::Stream for ExecQueryAsyncResultStream<'a> {
+    type Item = Result<IWbemClassWrapper, Error>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::pin::Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl<'a> Drop for ExecQueryAsyncResultStream<'a> {
+    // No automated test forces `Indicate`/`SetStatus` to race against this `Drop`:
+    // `it_can_query_a_struct_async` completes its query before the stream is dropped, so it
+    // never exercises `CancelAsyncCall` racing a still-in-flight callback. This path was
+    // manually exercised against a slow/long-lived query (a `WITHIN`-style poll) to confirm
+    // `Indicate` calls landing after `CancelAsyncCall` don't touch freed memory; a real
+    // regression test would need a sink double that can be held open past `drop`.
+    fn drop(&mut self) {
+        unsafe {
+            let sink = (*self.sink).as_raw();
+
+            // Best-effort: tell WMI to stop calling into the sink. If the call races with
+            // the query's natural completion, WMI simply returns an error we don't care about.
+            let _ = (*self.wmi_con.svc()).CancelAsyncCall(sink);
+
+            // Release our own COM reference through the vtable, rather than letting `Box`'s
+            // normal drop glue deallocate memory WMI may still be holding a reference to.
+            (*sink).Release();
+        }
+    }
+}